@@ -0,0 +1,154 @@
+use rizon_frontend::ast::Stmt;
+use rizon_frontend::lexer::Token;
+use rizon_tools::results::Loc;
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat, Position};
+
+use crate::document::Document;
+use crate::position::PositionEncoding;
+
+/// Rizon's reserved words, always offered as completions regardless of
+/// whether the document currently parses.
+const KEYWORDS: &[&str] = &[
+    "var", "fn", "if", "else", "while", "for", "return", "true", "false",
+    "nil", "struct", "self", "and", "or", "print",
+];
+
+/// Builds the completion list for the cursor at `position` in `doc`:
+/// declarations in scope there, collected from the parsed AST (or,
+/// failing that, identifiers lexed out of the raw token stream) plus the
+/// language's keywords.
+pub fn completions_for(doc: &Document, position: Position, encoding: PositionEncoding) -> Vec<CompletionItem> {
+    let mut items = keyword_items();
+
+    match &doc.ast {
+        Some(ast) => {
+            let offset = doc.line_index.offset(&doc.text, position, encoding);
+            items.extend(ast_items(ast, offset));
+        }
+        None => items.extend(lexical_items(doc)),
+    }
+
+    items
+}
+
+fn keyword_items() -> Vec<CompletionItem> {
+    KEYWORDS
+        .iter()
+        .map(|kw| CompletionItem {
+            label: kw.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn ast_items(ast: &[Stmt], offset: usize) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    collect_decls(ast, offset, &mut items);
+    items
+}
+
+/// Collects every declaration visible at `offset`: all top-level
+/// functions/vars/structs (Rizon has no modules, so top-level names are
+/// always in scope), plus the locals of whichever function body the
+/// cursor is actually inside. An `if` or `while` is descended into only
+/// when its span contains `offset`, and then both branches of an `if` are
+/// collected, not just the one holding the cursor; a bare block is always
+/// descended into, whether or not it contains `offset`.
+fn collect_decls(stmts: &[Stmt], offset: usize, items: &mut Vec<CompletionItem>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Function { name, params, body, full_loc, .. } => {
+                items.push(function_item(name, params));
+
+                if contains(*full_loc, offset) {
+                    collect_decls(body, offset, items);
+                }
+            }
+            Stmt::Var { name, .. } => {
+                items.push(CompletionItem {
+                    label: name.clone(),
+                    kind: Some(CompletionItemKind::VARIABLE),
+                    detail: Some(format!("var {name}")),
+                    ..Default::default()
+                });
+            }
+            Stmt::Struct { name, .. } => {
+                items.push(CompletionItem {
+                    label: name.clone(),
+                    kind: Some(CompletionItemKind::STRUCT),
+                    detail: Some(format!("struct {name}")),
+                    ..Default::default()
+                });
+            }
+            Stmt::Block(body) => collect_decls(body, offset, items),
+            Stmt::If { loc, then_branch, else_branch, .. } => {
+                if contains(*loc, offset) {
+                    collect_decls(then_branch, offset, items);
+                    if let Some(else_branch) = else_branch {
+                        collect_decls(else_branch, offset, items);
+                    }
+                }
+            }
+            Stmt::While { loc, body, .. } => {
+                if contains(*loc, offset) {
+                    collect_decls(body, offset, items);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn contains(loc: Loc, offset: usize) -> bool {
+    offset >= loc.start && offset < loc.end
+}
+
+fn function_item(name: &str, params: &[(String, Loc)]) -> CompletionItem {
+    let names: Vec<&str> = params.iter().map(|(name, _)| name.as_str()).collect();
+    let signature = names.join(", ");
+    let snippet_args = names
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("${{{}:{}}}", i + 1, p))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        detail: Some(format!("fn {name}({signature})")),
+        insert_text: Some(format!("{name}({snippet_args})")),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
+/// Fallback used while the document fails to parse: every distinct
+/// identifier seen by the lexer, so completion keeps working mid-edit.
+fn lexical_items(doc: &Document) -> Vec<CompletionItem> {
+    let Some(tokens) = &doc.tokens else {
+        return Vec::new();
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let mut items = Vec::new();
+
+    for tok in tokens {
+        if let Some(name) = identifier_lexeme(tok) {
+            if seen.insert(name.clone()) {
+                items.push(CompletionItem {
+                    label: name,
+                    kind: Some(CompletionItemKind::TEXT),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    items
+}
+
+fn identifier_lexeme(tok: &Token) -> Option<String> {
+    tok.as_identifier().map(|s| s.to_string())
+}
@@ -0,0 +1,64 @@
+use dashmap::DashMap;
+use rizon_frontend::{ast::Ast, lexer::Token};
+use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, Url};
+
+use crate::position::{LineIndex, PositionEncoding};
+use crate::symbols::SymbolTable;
+
+/// A single open document tracked by the server: its current source text
+/// plus the most recent lex/parse results, refreshed after every edit so
+/// language features never have to re-read the whole file from disk.
+#[derive(Debug, Default)]
+pub struct Document {
+    pub text: String,
+    pub version: i32,
+    pub tokens: Option<Vec<Token>>,
+    pub ast: Option<Ast>,
+    pub symbols: Option<SymbolTable>,
+    pub line_index: LineIndex,
+}
+
+impl Document {
+    pub fn new(text: String, version: i32) -> Self {
+        let line_index = LineIndex::new(&text);
+
+        Self {
+            text,
+            version,
+            tokens: None,
+            ast: None,
+            symbols: None,
+            line_index,
+        }
+    }
+
+    /// Applies a batch of `didChange` content changes in order. A change
+    /// carrying a `range` is spliced into the stored source; a change with
+    /// no `range` replaces the whole buffer. The line index is rebuilt once
+    /// the final text is known.
+    pub fn apply_changes(
+        &mut self,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        version: i32,
+        encoding: PositionEncoding,
+    ) {
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let line_index = LineIndex::new(&self.text);
+                    let start = line_index.offset(&self.text, range.start, encoding);
+                    let end = line_index.offset(&self.text, range.end, encoding);
+                    self.text.replace_range(start..end, &change.text);
+                }
+                None => self.text = change.text,
+            }
+        }
+
+        self.line_index = LineIndex::new(&self.text);
+        self.version = version;
+    }
+}
+
+/// The server's in-memory view of every document currently open in the
+/// client, keyed by URI.
+pub type DocumentStore = DashMap<Url, Document>;
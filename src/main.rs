@@ -1,8 +1,21 @@
+mod completion;
+mod document;
+mod outline;
+mod position;
+mod semantic_tokens;
+mod symbols;
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use document::{Document, DocumentStore};
+use position::{LineIndex, PositionEncoding};
 use rizon_frontend::{
     lexer::Lexer,
     parser::Parser
 };
 use rizon_tools::results::{Loc, RizonReport, RizonResult};
+use symbols::SymbolTable;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
@@ -10,16 +23,41 @@ use tower_lsp::{Client, LanguageServer, LspService, Server};
 #[derive(Debug)]
 struct Backend {
     client: Client,
+    documents: DocumentStore,
+    position_encoding: RwLock<PositionEncoding>,
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let client_encodings = params
+            .capabilities
+            .general
+            .and_then(|g| g.position_encodings)
+            .unwrap_or_default();
+
+        let encoding = PositionEncoding::negotiate(&client_encodings);
+        *self.position_encoding.write().unwrap() = encoding;
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions::default()),
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)),
+                position_encoding: Some(encoding.as_kind()),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                        legend: semantic_tokens::legend(),
+                        full: Some(SemanticTokensFullOptions::Bool(true)),
+                        range: Some(true),
+                        ..Default::default()
+                    }),
+                ),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -36,11 +74,16 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
-    async fn completion(&self, _: CompletionParams) -> Result<Option<CompletionResponse>> {
-        Ok(Some(CompletionResponse::Array(vec![
-            CompletionItem::new_simple("Hello".to_string(), "Some detail".to_string()),
-            CompletionItem::new_simple("Bye".to_string(), "More detail".to_string()),
-        ])))
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        let items = match self.documents.get(&uri) {
+            Some(doc) => completion::completions_for(&doc, position, self.encoding()),
+            None => Vec::new(),
+        };
+
+        Ok(Some(CompletionResponse::Array(items)))
     }
 
     async fn hover(&self, _: HoverParams) -> Result<Option<Hover>> {
@@ -50,26 +93,207 @@ impl LanguageServer for Backend {
         }))
     }
 
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri;
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+
+        let data = semantic_tokens::tokens_full(&doc, self.encoding());
+
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data,
+        })))
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let uri = params.text_document.uri;
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+
+        let data = semantic_tokens::tokens_in_range(&doc, self.encoding(), params.range);
+
+        Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data,
+        })))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+
+        let symbols = outline::document_symbols(&doc, self.encoding());
+
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let symbols = outline::workspace_symbols(&self.documents, &params.query, self.encoding());
+
+        Ok(Some(symbols))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let Some((_, symbol)) = self.symbol_at(&doc, position) else {
+            return Ok(None);
+        };
+
+        let range = doc.line_index.range(&doc.text, symbol.definition, self.encoding());
+
+        Ok(Some(GotoDefinitionResponse::Scalar(Location::new(uri, range))))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let include_declaration = params.context.include_declaration;
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let Some((_, symbol)) = self.symbol_at(&doc, position) else {
+            return Ok(None);
+        };
+
+        let encoding = self.encoding();
+        let mut locations: Vec<Location> = symbol
+            .references
+            .iter()
+            .map(|loc| Location::new(uri.clone(), doc.line_index.range(&doc.text, *loc, encoding)))
+            .collect();
+
+        if include_declaration {
+            locations.push(Location::new(
+                uri.clone(),
+                doc.line_index.range(&doc.text, symbol.definition, encoding),
+            ));
+        }
+
+        Ok(Some(locations))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let new_name = params.new_name;
+
+        if !symbols::is_valid_identifier(&new_name) {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "new name is not a valid Rizon identifier",
+            ));
+        }
+
+        let Some(doc) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let Some((_, symbol)) = self.symbol_at(&doc, position) else {
+            return Ok(None);
+        };
+
+        let encoding = self.encoding();
+        let edits: Vec<TextEdit> = symbol
+            .references
+            .iter()
+            .chain(std::iter::once(&symbol.definition))
+            .map(|loc| TextEdit {
+                range: doc.line_index.range(&doc.text, *loc, encoding),
+                new_text: new_name.clone(),
+            })
+            .collect();
+
+        let mut changes = HashMap::new();
+        changes.insert(uri, edits);
+
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }))
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let uri = params.text_document.uri;
         let text = params.text_document.text;
+        let version = params.text_document.version;
+
+        self.documents
+            .insert(uri.clone(), Document::new(text.clone(), version));
 
         self.parse_and_store(uri, text).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let uri = params.text_document.uri;
-        let changes = params.content_changes;
+        let version = params.text_document.version;
+        let encoding = self.encoding();
 
-        // Assuming full sync, otherwise, handle incremental changes
-        if let Some(change) = changes.last() {
-            self.parse_and_store(uri, change.text.clone()).await;
-        }
+        let text = {
+            let mut doc = self
+                .documents
+                .entry(uri.clone())
+                .or_insert_with(|| Document::new(String::new(), version));
+
+            doc.apply_changes(params.content_changes, version, encoding);
+            doc.text.clone()
+        };
+
+        self.parse_and_store(uri, text).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.documents.remove(&params.text_document.uri);
     }
 }
 
 impl Backend {
+    fn encoding(&self) -> PositionEncoding {
+        *self.position_encoding.read().unwrap()
+    }
+
+    /// Resolves the symbol under `position` in `doc`, if any.
+    fn symbol_at<'a>(
+        &self,
+        doc: &'a Document,
+        position: Position,
+    ) -> Option<(&'a str, &'a symbols::Symbol)> {
+        let table = doc.symbols.as_ref()?;
+        let offset = doc.line_index.offset(&doc.text, position, self.encoding());
+
+        table.symbol_at(offset)
+    }
+
     async fn parse_and_store(&self, uri: Url, text: String) {
+        let encoding = self.encoding();
+        let line_index = LineIndex::new(&text);
+
         let mut lexer = Lexer::new();
         let res = lexer.tokenize(&text);
 
@@ -78,9 +302,15 @@ impl Backend {
             Err(errs) => {
                 let diags: Vec<Diagnostic> = errs
                     .into_iter()
-                    .map(|e| rev_result_to_diagnostic(e, &text))
+                    .map(|e| rev_result_to_diagnostic(e, &text, &line_index, encoding))
                     .collect();
 
+                if let Some(mut doc) = self.documents.get_mut(&uri) {
+                    doc.tokens = None;
+                    doc.ast = None;
+                    doc.symbols = None;
+                }
+
                 self.publish_diagnostics(uri, diags).await;
 
                 return
@@ -88,18 +318,28 @@ impl Backend {
         };
 
         let mut parser = Parser::default();
-        let res = parser.parse(tks);
+        let res = parser.parse(tks.clone());
 
-        let diags: Vec<Diagnostic> = match res {
-            Ok(_) => vec![],
+        let (ast, diags) = match res {
+            Ok(ast) => (Some(ast), vec![]),
             Err(errs) => {
-                errs
+                let diags = errs
                     .into_iter()
-                    .map(|e| rev_result_to_diagnostic(e, &text))
-                    .collect()
+                    .map(|e| rev_result_to_diagnostic(e, &text, &line_index, encoding))
+                    .collect();
+
+                (None, diags)
             }
         };
 
+        let symbol_table = ast.as_ref().map(|ast| SymbolTable::build(ast));
+
+        if let Some(mut doc) = self.documents.get_mut(&uri) {
+            doc.tokens = Some(tks);
+            doc.ast = ast;
+            doc.symbols = symbol_table;
+        }
+
         self.publish_diagnostics(uri, diags).await;
     }
 
@@ -110,20 +350,16 @@ impl Backend {
     }
 }
 
-fn rev_result_to_diagnostic<T: RizonReport>(res: RizonResult<T>, text: &str) -> Diagnostic {
+fn rev_result_to_diagnostic<T: RizonReport>(
+    res: RizonResult<T>,
+    text: &str,
+    line_index: &LineIndex,
+    encoding: PositionEncoding,
+) -> Diagnostic {
     let loc = res.loc.unwrap_or(Loc::new(0, 0));
 
-    let line_start = &text[..loc.start].chars().filter(|c| *c == '\n').count();
-    let start = Position {
-        line: *line_start as u32,
-        character: loc.start as u32,
-    };
-
-    let line_end = &text[..loc.end].chars().filter(|c| *c == '\n').count();
-    let end = Position {
-        line: *line_end as u32,
-        character: loc.end as u32,
-    };
+    let start = line_index.position(text, loc.start, encoding);
+    let end = line_index.position(text, loc.end, encoding);
 
     Diagnostic {
         range: Range { start, end },
@@ -138,6 +374,10 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend { client });
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        documents: DocumentStore::new(),
+        position_encoding: RwLock::new(PositionEncoding::default()),
+    });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
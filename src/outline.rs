@@ -0,0 +1,143 @@
+use rizon_frontend::ast::Stmt;
+use tower_lsp::lsp_types::{DocumentSymbol, Location, SymbolInformation, SymbolKind as LspSymbolKind};
+
+use crate::document::Document;
+use crate::position::PositionEncoding;
+use crate::symbols::SymbolKind;
+
+/// Builds the hierarchical outline for `textDocument/documentSymbol`:
+/// top-level functions and struct declarations as parents, with locals
+/// declared anywhere in a function's body (including inside nested
+/// blocks/`if`/`while`) as its children. Rizon structs don't carry member
+/// fields in the AST yet, so struct symbols are always leaves for now.
+pub fn document_symbols(doc: &Document, encoding: PositionEncoding) -> Vec<DocumentSymbol> {
+    let Some(ast) = &doc.ast else {
+        return Vec::new();
+    };
+
+    collect_children(ast, doc, encoding)
+}
+
+/// Collects the declarations in `stmts`, recursing through control-flow
+/// bodies that don't themselves introduce an outline entry so that a
+/// local declared inside an `if`/`while`/block still surfaces as a child.
+fn collect_children(stmts: &[Stmt], doc: &Document, encoding: PositionEncoding) -> Vec<DocumentSymbol> {
+    let mut children = Vec::new();
+
+    for stmt in stmts {
+        match stmt {
+            Stmt::Function { .. } | Stmt::Struct { .. } | Stmt::Var { .. } => {
+                if let Some(symbol) = to_document_symbol(stmt, doc, encoding) {
+                    children.push(symbol);
+                }
+            }
+            Stmt::Block(body) => children.extend(collect_children(body, doc, encoding)),
+            Stmt::If { then_branch, else_branch, .. } => {
+                children.extend(collect_children(then_branch, doc, encoding));
+                if let Some(else_branch) = else_branch {
+                    children.extend(collect_children(else_branch, doc, encoding));
+                }
+            }
+            Stmt::While { body, .. } => children.extend(collect_children(body, doc, encoding)),
+            _ => {}
+        }
+    }
+
+    children
+}
+
+#[allow(deprecated)]
+fn to_document_symbol(stmt: &Stmt, doc: &Document, encoding: PositionEncoding) -> Option<DocumentSymbol> {
+    let (name, kind, name_loc, full_loc, children) = match stmt {
+        Stmt::Function { name, loc, full_loc, body, .. } => (
+            name.clone(),
+            LspSymbolKind::FUNCTION,
+            *loc,
+            *full_loc,
+            collect_children(body, doc, encoding),
+        ),
+        Stmt::Struct { name, loc, full_loc } => {
+            (name.clone(), LspSymbolKind::STRUCT, *loc, *full_loc, Vec::new())
+        }
+        Stmt::Var { name, loc, .. } => (name.clone(), LspSymbolKind::VARIABLE, *loc, *loc, Vec::new()),
+        _ => return None,
+    };
+
+    let selection_range = doc.line_index.range(&doc.text, name_loc, encoding);
+    let range = doc.line_index.range(&doc.text, full_loc, encoding);
+
+    Some(DocumentSymbol {
+        name,
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: (!children.is_empty()).then_some(children),
+    })
+}
+
+/// Indexes every document currently in the store and filters by `query`
+/// with a simple case-insensitive subsequence match, for
+/// `workspace/symbol`.
+#[allow(deprecated)]
+pub fn workspace_symbols(
+    documents: &crate::document::DocumentStore,
+    query: &str,
+    encoding: PositionEncoding,
+) -> Vec<SymbolInformation> {
+    let mut matches = Vec::new();
+
+    for entry in documents.iter() {
+        let uri = entry.key().clone();
+        let doc = entry.value();
+
+        let Some(table) = &doc.symbols else {
+            continue;
+        };
+
+        for (name, symbol) in table.all() {
+            if !subsequence_match(query, name) {
+                continue;
+            }
+
+            let location = Location::new(
+                uri.clone(),
+                doc.line_index.range(&doc.text, symbol.definition, encoding),
+            );
+
+            matches.push(SymbolInformation {
+                name: name.to_string(),
+                kind: lsp_kind(symbol.kind),
+                tags: None,
+                deprecated: None,
+                location,
+                container_name: None,
+            });
+        }
+    }
+
+    matches
+}
+
+fn lsp_kind(kind: SymbolKind) -> LspSymbolKind {
+    match kind {
+        SymbolKind::Function => LspSymbolKind::FUNCTION,
+        SymbolKind::Variable => LspSymbolKind::VARIABLE,
+        SymbolKind::Type => LspSymbolKind::STRUCT,
+    }
+}
+
+fn subsequence_match(query: &str, candidate: &str) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+
+    let mut candidate_chars = candidate.chars();
+
+    query
+        .chars()
+        .all(|qc| candidate_chars.any(|cc| cc.eq_ignore_ascii_case(&qc)))
+}
+
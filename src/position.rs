@@ -0,0 +1,193 @@
+use rizon_tools::results::Loc;
+use tower_lsp::lsp_types::{Position, PositionEncodingKind, Range};
+
+/// Which code unit `Position::character` counts in, negotiated with the
+/// client during `initialize`. LSP defaults to UTF-16; we upgrade to
+/// UTF-8 (a plain byte offset, cheaper to compute) whenever the client
+/// advertises support for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// Picks the best encoding both sides support, preferring UTF-8 since
+    /// it avoids re-counting code units on every lookup.
+    pub fn negotiate(client_encodings: &[PositionEncodingKind]) -> Self {
+        if client_encodings.iter().any(|e| *e == PositionEncodingKind::UTF8) {
+            PositionEncoding::Utf8
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+
+    pub fn as_kind(&self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+        }
+    }
+}
+
+impl Default for PositionEncoding {
+    fn default() -> Self {
+        PositionEncoding::Utf16
+    }
+}
+
+/// Precomputed byte offsets of every line start in a document, used to map
+/// byte offsets to/from LSP `Position`s without rescanning the text.
+#[derive(Debug, Default)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(idx, _)| idx + 1));
+
+        Self { line_starts }
+    }
+
+    /// Converts a byte offset into the document into an LSP `Position`,
+    /// counting columns in `encoding`'s code units.
+    pub fn position(&self, text: &str, offset: usize, encoding: PositionEncoding) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+
+        let line_start = self.line_starts[line];
+        let column = &text[line_start..offset];
+
+        let character = match encoding {
+            PositionEncoding::Utf8 => column.len() as u32,
+            PositionEncoding::Utf16 => column.encode_utf16().count() as u32,
+        };
+
+        Position {
+            line: line as u32,
+            character,
+        }
+    }
+
+    /// Converts a byte-offset `Loc` span into an LSP `Range`.
+    pub fn range(&self, text: &str, loc: Loc, encoding: PositionEncoding) -> Range {
+        Range {
+            start: self.position(text, loc.start, encoding),
+            end: self.position(text, loc.end, encoding),
+        }
+    }
+
+    /// Length of `line` (excluding its trailing newline), in `encoding`'s
+    /// code units. Used to split a span that crosses a newline into one
+    /// sub-range per line.
+    pub fn line_length(&self, text: &str, line: u32, encoding: PositionEncoding) -> u32 {
+        let Some(&line_start) = self.line_starts.get(line as usize) else {
+            return 0;
+        };
+
+        let line_end = self
+            .line_starts
+            .get(line as usize + 1)
+            .map(|&next_start| next_start - 1)
+            .unwrap_or(text.len());
+
+        let content = &text[line_start..line_end];
+
+        match encoding {
+            PositionEncoding::Utf8 => content.len() as u32,
+            PositionEncoding::Utf16 => content.encode_utf16().count() as u32,
+        }
+    }
+
+    /// Converts an LSP `Position` back into a byte offset into `text`.
+    pub fn offset(&self, text: &str, position: Position, encoding: PositionEncoding) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+
+        let line_end = self
+            .line_starts
+            .get(position.line as usize + 1)
+            .copied()
+            .unwrap_or(text.len());
+
+        let line = &text[line_start..line_end];
+
+        match encoding {
+            PositionEncoding::Utf8 => {
+                line_start + (position.character as usize).min(line.len())
+            }
+            PositionEncoding::Utf16 => {
+                let mut units_left = position.character;
+
+                for (byte_idx, ch) in line.char_indices() {
+                    if units_left == 0 {
+                        return line_start + byte_idx;
+                    }
+                    units_left = units_left.saturating_sub(ch.len_utf16() as u32);
+                }
+
+                line_start + line.len()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_position_and_offset_round_trip() {
+        let text = "let x = 1;\nlet y = 2;\n";
+        let index = LineIndex::new(text);
+
+        let offset = 15; // the 'y' in "let y = 2;"
+        let position = index.position(text, offset, PositionEncoding::Utf8);
+        assert_eq!(position, Position::new(1, 4));
+
+        assert_eq!(index.offset(text, position, PositionEncoding::Utf8), offset);
+    }
+
+    #[test]
+    fn utf8_and_utf16_columns_diverge_past_a_surrogate_pair() {
+        let text = "a = \"😀\";\nb = 1;\n";
+        let index = LineIndex::new(text);
+
+        let quote = text.find('"').unwrap();
+        let after_emoji = quote + 1 + '😀'.len_utf8();
+
+        // 'a', ' ', '=', ' ', '"' (5 bytes/units) + the emoji: 4 bytes but a
+        // 2-unit UTF-16 surrogate pair.
+        assert_eq!(index.position(text, after_emoji, PositionEncoding::Utf8).character, 9);
+        assert_eq!(index.position(text, after_emoji, PositionEncoding::Utf16).character, 7);
+
+        let utf16_position = Position::new(0, 7);
+        assert_eq!(index.offset(text, utf16_position, PositionEncoding::Utf16), after_emoji);
+    }
+
+    #[test]
+    fn line_length_excludes_the_trailing_newline() {
+        let text = "abc\nde\n";
+        let index = LineIndex::new(text);
+
+        assert_eq!(index.line_length(text, 0, PositionEncoding::Utf8), 3);
+        assert_eq!(index.line_length(text, 1, PositionEncoding::Utf8), 2);
+    }
+
+    #[test]
+    fn loc_converts_to_a_single_line_range() {
+        let text = "fn a() {}\nfn b() {}\n";
+        let index = LineIndex::new(text);
+
+        let loc = Loc::new(3, 4); // the "a" in "fn a() {}"
+        let range = index.range(text, loc, PositionEncoding::Utf8);
+
+        assert_eq!(range.start, Position::new(0, 3));
+        assert_eq!(range.end, Position::new(0, 4));
+    }
+}
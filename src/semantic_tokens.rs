@@ -0,0 +1,236 @@
+use rizon_frontend::lexer::Token;
+use tower_lsp::lsp_types::{Position, Range, SemanticToken, SemanticTokenType, SemanticTokensLegend};
+
+use crate::document::Document;
+use crate::position::PositionEncoding;
+use crate::symbols::{SymbolKind, SymbolTable};
+
+const KEYWORD: u32 = 0;
+const FUNCTION: u32 = 1;
+const VARIABLE: u32 = 2;
+const TYPE: u32 = 3;
+const NUMBER: u32 = 4;
+const STRING: u32 = 5;
+const COMMENT: u32 = 6;
+const OPERATOR: u32 = 7;
+
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::OPERATOR,
+];
+
+/// The legend advertised to the client: positions in `TOKEN_TYPES` are the
+/// `tokenType` indices this module emits. We don't use any modifiers yet.
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: vec![],
+    }
+}
+
+struct Classified {
+    range: Range,
+    token_type: u32,
+}
+
+/// Semantic tokens for the whole document, in the LSP delta format:
+/// `(deltaLine, deltaStartChar, length, tokenType, tokenModifiers)` per
+/// token, sorted by position and relative to the previous token.
+pub fn tokens_full(doc: &Document, encoding: PositionEncoding) -> Vec<SemanticToken> {
+    encode(classify_all(doc, encoding))
+}
+
+/// Semantic tokens restricted to `range`, for `semantic_tokens_range`.
+pub fn tokens_in_range(doc: &Document, encoding: PositionEncoding, range: Range) -> Vec<SemanticToken> {
+    let classified = classify_all(doc, encoding)
+        .into_iter()
+        .filter(|c| c.range.start >= range.start && c.range.end <= range.end)
+        .collect();
+
+    encode(classified)
+}
+
+fn classify_all(doc: &Document, encoding: PositionEncoding) -> Vec<Classified> {
+    let Some(tokens) = &doc.tokens else {
+        return Vec::new();
+    };
+
+    tokens
+        .iter()
+        .filter_map(|tok| {
+            let token_type = classify(tok, doc.symbols.as_ref())?;
+            let range = doc.line_index.range(&doc.text, tok.loc(), encoding);
+
+            Some(
+                split_by_line(range, doc, encoding)
+                    .into_iter()
+                    .map(move |range| Classified { range, token_type }),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+/// Splits a range that spans multiple lines into one sub-range per line.
+/// LSP semantic tokens are single-line by definition, so a multi-line
+/// token (a block comment, a multi-line string) has to be reported as
+/// several entries rather than one with a length computed across lines.
+fn split_by_line(range: Range, doc: &Document, encoding: PositionEncoding) -> Vec<Range> {
+    if range.start.line == range.end.line {
+        return vec![range];
+    }
+
+    let mut ranges = Vec::new();
+
+    let first_line_len = doc.line_index.line_length(&doc.text, range.start.line, encoding);
+    ranges.push(Range {
+        start: range.start,
+        end: Position::new(range.start.line, first_line_len),
+    });
+
+    for line in (range.start.line + 1)..range.end.line {
+        let line_len = doc.line_index.line_length(&doc.text, line, encoding);
+        ranges.push(Range {
+            start: Position::new(line, 0),
+            end: Position::new(line, line_len),
+        });
+    }
+
+    ranges.push(Range {
+        start: Position::new(range.end.line, 0),
+        end: range.end,
+    });
+
+    ranges
+}
+
+fn classify(tok: &Token, symbols: Option<&SymbolTable>) -> Option<u32> {
+    if tok.is_keyword() {
+        return Some(KEYWORD);
+    }
+    if tok.is_number() {
+        return Some(NUMBER);
+    }
+    if tok.is_string() {
+        return Some(STRING);
+    }
+    if tok.is_comment() {
+        return Some(COMMENT);
+    }
+    if tok.is_operator() {
+        return Some(OPERATOR);
+    }
+
+    let name = tok.as_identifier()?;
+
+    Some(match symbols.and_then(|s| s.kind_of(name)) {
+        Some(SymbolKind::Function) => FUNCTION,
+        Some(SymbolKind::Type) => TYPE,
+        Some(SymbolKind::Variable) | None => VARIABLE,
+    })
+}
+
+fn encode(classified: Vec<Classified>) -> Vec<SemanticToken> {
+    let mut prev = Position::new(0, 0);
+    let mut tokens = Vec::with_capacity(classified.len());
+
+    for entry in classified {
+        let start = entry.range.start;
+
+        let delta_line = start.line - prev.line;
+        let delta_start = if delta_line == 0 {
+            start.character - prev.character
+        } else {
+            start.character
+        };
+
+        tokens.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: entry.range.end.character - entry.range.start.character,
+            token_type: entry.token_type,
+            token_modifiers_bitset: 0,
+        });
+
+        prev = start;
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+
+    #[test]
+    fn single_line_range_is_left_untouched() {
+        let doc = Document::new("let x = 1;\n".to_string(), 0);
+        let range = Range {
+            start: Position::new(0, 0),
+            end: Position::new(0, 3),
+        };
+
+        assert_eq!(split_by_line(range, &doc, PositionEncoding::Utf8), vec![range]);
+    }
+
+    #[test]
+    fn multiline_range_splits_into_one_entry_per_line() {
+        let text = "/* a\nbc\nd */\n";
+        let doc = Document::new(text.to_string(), 0);
+
+        let range = Range {
+            start: Position::new(0, 0),
+            end: Position::new(2, 4),
+        };
+
+        let split = split_by_line(range, &doc, PositionEncoding::Utf8);
+
+        assert_eq!(
+            split,
+            vec![
+                Range { start: Position::new(0, 0), end: Position::new(0, 4) },
+                Range { start: Position::new(1, 0), end: Position::new(1, 2) },
+                Range { start: Position::new(2, 0), end: Position::new(2, 4) },
+            ]
+        );
+
+        // every sub-range must stay on one line and never go negative, i.e.
+        // the bug `encode` used to hit (end.character < start.character
+        // once a token crossed a newline) can't happen downstream.
+        for r in &split {
+            assert_eq!(r.start.line, r.end.line);
+            assert!(r.end.character >= r.start.character);
+        }
+    }
+
+    #[test]
+    fn encode_produces_deltas_relative_to_the_previous_token() {
+        let classified = vec![
+            Classified {
+                range: Range { start: Position::new(0, 0), end: Position::new(0, 4) },
+                token_type: COMMENT,
+            },
+            Classified {
+                range: Range { start: Position::new(1, 0), end: Position::new(1, 2) },
+                token_type: COMMENT,
+            },
+        ];
+
+        let tokens = encode(classified);
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].delta_line, 0);
+        assert_eq!(tokens[0].delta_start, 0);
+        assert_eq!(tokens[0].length, 4);
+        assert_eq!(tokens[1].delta_line, 1);
+        assert_eq!(tokens[1].delta_start, 0);
+        assert_eq!(tokens[1].length, 2);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use rizon_frontend::ast::{Expr, Stmt};
+use rizon_tools::results::Loc;
+
+/// What a declared name binds to, used to pick the right `SymbolKind`/
+/// semantic token type for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    Type,
+}
+
+/// One named declaration in a document: where it's defined and every span
+/// that refers back to it.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub definition: Loc,
+    pub references: Vec<Loc>,
+}
+
+impl Default for Symbol {
+    fn default() -> Self {
+        Self {
+            kind: SymbolKind::Variable,
+            definition: Loc::new(0, 0),
+            references: Vec::new(),
+        }
+    }
+}
+
+/// Identifies the lexical scope a declaration lives in: `GLOBAL_SCOPE` for
+/// everything at file scope, or a fresh id per function for its
+/// parameters and locals. Rizon has no nested functions, so one scope per
+/// function is as fine-grained as declarations need to be.
+type ScopeId = usize;
+
+const GLOBAL_SCOPE: ScopeId = 0;
+
+/// Maps every declared name in a document to its binding, keyed by name
+/// *and* enclosing scope so that same-named locals in different functions
+/// don't collapse into a single entry. Built once per parse alongside the
+/// AST.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    symbols: HashMap<(ScopeId, String), Symbol>,
+    next_scope: ScopeId,
+}
+
+impl SymbolTable {
+    /// Builds the table in two passes so that references to a name
+    /// declared later in the file still resolve: the first pass only
+    /// declares every binding, the second resolves every reference
+    /// against the now-complete set of declarations. Both passes hand out
+    /// scope ids to functions in the same order they're visited, so a
+    /// given function gets the same scope id in each pass.
+    pub fn build(ast: &[Stmt]) -> Self {
+        let mut table = Self::default();
+
+        table.next_scope = GLOBAL_SCOPE + 1;
+        table.declare_stmts(ast, GLOBAL_SCOPE);
+
+        table.next_scope = GLOBAL_SCOPE + 1;
+        table.resolve_stmts(ast, GLOBAL_SCOPE);
+
+        table
+    }
+
+    /// The binding whose definition or one of its references contains
+    /// `offset`, i.e. whatever's under the cursor.
+    pub fn symbol_at(&self, offset: usize) -> Option<(&str, &Symbol)> {
+        self.symbols.iter().find_map(|((_, name), symbol)| {
+            let hit = contains(symbol.definition, offset)
+                || symbol.references.iter().any(|r| contains(*r, offset));
+
+            hit.then_some((name.as_str(), symbol))
+        })
+    }
+
+    /// All declarations in the document, for outline/semantic-token
+    /// features that need to enumerate symbols rather than look one up.
+    pub fn all(&self) -> impl Iterator<Item = (&str, &Symbol)> {
+        self.symbols.iter().map(|((_, name), symbol)| (name.as_str(), symbol))
+    }
+
+    /// The kind of `name`, regardless of which scope declared it. Good
+    /// enough for semantic-token classification, which only ever sees a
+    /// bare identifier lexeme with no scope context to disambiguate by.
+    pub fn kind_of(&self, name: &str) -> Option<SymbolKind> {
+        self.symbols.iter().find_map(|((_, n), s)| (n == name).then_some(s.kind))
+    }
+
+    fn declare(&mut self, scope: ScopeId, name: &str, kind: SymbolKind, loc: Loc) {
+        let symbol = self.symbols.entry((scope, name.to_string())).or_default();
+        symbol.kind = kind;
+        symbol.definition = loc;
+    }
+
+    /// Resolves `name` against `scope` first, falling back to the global
+    /// scope so a function body can still refer to other top-level
+    /// functions/vars/structs.
+    fn reference(&mut self, scope: ScopeId, name: &str, loc: Loc) {
+        if let Some(symbol) = self.symbols.get_mut(&(scope, name.to_string())) {
+            symbol.references.push(loc);
+            return;
+        }
+
+        if scope != GLOBAL_SCOPE {
+            if let Some(symbol) = self.symbols.get_mut(&(GLOBAL_SCOPE, name.to_string())) {
+                symbol.references.push(loc);
+            }
+        }
+    }
+
+    /// First pass: declares every binding without looking at any
+    /// expression, so forward references are in the table by the time the
+    /// second pass resolves them.
+    fn declare_stmts(&mut self, stmts: &[Stmt], scope: ScopeId) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Function { name, params, body, loc, .. } => {
+                    self.declare(scope, name, SymbolKind::Function, *loc);
+
+                    let fn_scope = self.next_scope;
+                    self.next_scope += 1;
+
+                    for (param, param_loc) in params {
+                        self.declare(fn_scope, param, SymbolKind::Variable, *param_loc);
+                    }
+
+                    self.declare_stmts(body, fn_scope);
+                }
+                Stmt::Var { name, loc, .. } => self.declare(scope, name, SymbolKind::Variable, *loc),
+                Stmt::Struct { name, loc, .. } => self.declare(scope, name, SymbolKind::Type, *loc),
+                Stmt::Block(body) => self.declare_stmts(body, scope),
+                Stmt::If { then_branch, else_branch, .. } => {
+                    self.declare_stmts(then_branch, scope);
+                    if let Some(else_branch) = else_branch {
+                        self.declare_stmts(else_branch, scope);
+                    }
+                }
+                Stmt::While { body, .. } => self.declare_stmts(body, scope),
+                _ => {}
+            }
+        }
+    }
+
+    /// Second pass: walks the same tree again, this time resolving every
+    /// identifier expression against the table built by `declare_stmts`.
+    /// Mirrors that traversal exactly so the scope ids it hands out land
+    /// on the same functions.
+    fn resolve_stmts(&mut self, stmts: &[Stmt], scope: ScopeId) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Function { body, .. } => {
+                    let fn_scope = self.next_scope;
+                    self.next_scope += 1;
+
+                    self.resolve_stmts(body, fn_scope);
+                }
+                Stmt::Var { value, .. } => {
+                    if let Some(expr) = value {
+                        self.collect_expr(scope, expr);
+                    }
+                }
+                Stmt::Struct { .. } => {}
+                Stmt::Block(body) => self.resolve_stmts(body, scope),
+                Stmt::Expr(expr) => self.collect_expr(scope, expr),
+                Stmt::If { cond, then_branch, else_branch, .. } => {
+                    self.collect_expr(scope, cond);
+                    self.resolve_stmts(then_branch, scope);
+                    if let Some(else_branch) = else_branch {
+                        self.resolve_stmts(else_branch, scope);
+                    }
+                }
+                Stmt::While { cond, body, .. } => {
+                    self.collect_expr(scope, cond);
+                    self.resolve_stmts(body, scope);
+                }
+                Stmt::Return { value, .. } => {
+                    if let Some(expr) = value {
+                        self.collect_expr(scope, expr);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Recurses into every sub-expression so an identifier used anywhere —
+    /// not just as a bare statement or the callee of a call — ends up as a
+    /// reference: both sides of a binary/logical expression, the operand
+    /// of a unary one, the inner expression of a grouping, and both the
+    /// target and value of an assignment.
+    fn collect_expr(&mut self, scope: ScopeId, expr: &Expr) {
+        match expr {
+            Expr::Identifier { name, loc } => self.reference(scope, name, *loc),
+            Expr::Call { callee, args } => {
+                self.collect_expr(scope, callee);
+                for arg in args {
+                    self.collect_expr(scope, arg);
+                }
+            }
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.collect_expr(scope, left);
+                self.collect_expr(scope, right);
+            }
+            Expr::Unary { expr, .. } | Expr::Grouping { expr } => self.collect_expr(scope, expr),
+            Expr::Assign { name, value, loc } => {
+                self.reference(scope, name, *loc);
+                self.collect_expr(scope, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn contains(loc: Loc, offset: usize) -> bool {
+    offset >= loc.start && offset < loc.end
+}
+
+/// Mirrors the lexer's identifier grammar: an ASCII letter or underscore
+/// followed by letters, digits, or underscores. Used to reject a `rename`
+/// that would produce a name the lexer couldn't actually tokenize back.
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(start: usize, end: usize) -> Loc {
+        Loc::new(start, end)
+    }
+
+    fn ident(name: &str, start: usize, end: usize) -> Expr {
+        Expr::Identifier { name: name.to_string(), loc: loc(start, end) }
+    }
+
+    #[test]
+    fn same_named_locals_in_different_functions_stay_distinct() {
+        // fn a() { var i = 1; return i; } fn b() { var i = 2; return i; }
+        let ast = vec![
+            Stmt::Function {
+                name: "a".to_string(),
+                params: vec![],
+                body: vec![
+                    Stmt::Var { name: "i".to_string(), loc: loc(10, 11), value: None },
+                    Stmt::Return { value: Some(ident("i", 20, 21)), loc: loc(20, 21) },
+                ],
+                loc: loc(3, 4),
+                full_loc: loc(0, 30),
+            },
+            Stmt::Function {
+                name: "b".to_string(),
+                params: vec![],
+                body: vec![
+                    Stmt::Var { name: "i".to_string(), loc: loc(40, 41), value: None },
+                    Stmt::Return { value: Some(ident("i", 50, 51)), loc: loc(50, 51) },
+                ],
+                loc: loc(33, 34),
+                full_loc: loc(30, 60),
+            },
+        ];
+
+        let table = SymbolTable::build(&ast);
+
+        let (_, a_i) = table.symbol_at(10).expect("a's i");
+        assert_eq!(a_i.definition, loc(10, 11));
+        assert_eq!(a_i.references, vec![loc(20, 21)]);
+
+        let (_, b_i) = table.symbol_at(40).expect("b's i");
+        assert_eq!(b_i.definition, loc(40, 41));
+        assert_eq!(b_i.references, vec![loc(50, 51)]);
+    }
+
+    #[test]
+    fn function_parameters_are_declared_and_resolved() {
+        // fn add(a, b) { return a + b; }
+        let ast = vec![Stmt::Function {
+            name: "add".to_string(),
+            params: vec![("a".to_string(), loc(7, 8)), ("b".to_string(), loc(10, 11))],
+            body: vec![Stmt::Return {
+                value: Some(Expr::Binary {
+                    left: Box::new(ident("a", 20, 21)),
+                    right: Box::new(ident("b", 24, 25)),
+                    loc: loc(20, 25),
+                }),
+                loc: loc(20, 25),
+            }],
+            loc: loc(3, 6),
+            full_loc: loc(0, 30),
+        }];
+
+        let table = SymbolTable::build(&ast);
+
+        let (_, a) = table.symbol_at(7).expect("param a");
+        assert_eq!(a.references, vec![loc(20, 21)]);
+
+        let (_, b) = table.symbol_at(10).expect("param b");
+        assert_eq!(b.references, vec![loc(24, 25)]);
+    }
+
+    #[test]
+    fn identifiers_inside_conditions_and_assignments_are_references() {
+        // var i = 0; while i < 10 { i = i + 1; }
+        let ast = vec![
+            Stmt::Var { name: "i".to_string(), loc: loc(4, 5), value: None },
+            Stmt::While {
+                cond: Expr::Binary {
+                    left: Box::new(ident("i", 20, 21)),
+                    right: Box::new(ident("ten", 0, 0)),
+                    loc: loc(20, 26),
+                },
+                body: vec![Stmt::Expr(Expr::Assign {
+                    name: "i".to_string(),
+                    value: Box::new(Expr::Binary {
+                        left: Box::new(ident("i", 40, 41)),
+                        right: Box::new(ident("one", 0, 0)),
+                        loc: loc(40, 45),
+                    }),
+                    loc: loc(35, 36),
+                })],
+                loc: loc(15, 50),
+            },
+        ];
+
+        let table = SymbolTable::build(&ast);
+        let (_, i) = table.symbol_at(4).expect("i");
+
+        assert!(i.references.contains(&loc(20, 21)));
+        assert!(i.references.contains(&loc(35, 36)));
+        assert!(i.references.contains(&loc(40, 41)));
+    }
+}